@@ -1,5 +1,3 @@
-use std::cmp::Ordering;
-
 use ecow::EcoString;
 
 use crate::ast::SrcSpan;
@@ -21,19 +19,12 @@ impl ModuleExtra {
 
     /// Detects if a byte index is in a comment context
     pub fn is_within_comment(&self, byte_index: u32) -> bool {
-        let cmp = |span: &SrcSpan| {
-            if byte_index < span.start {
-                Ordering::Greater
-            } else if byte_index > span.end {
-                Ordering::Less
-            } else {
-                Ordering::Equal
-            }
+        let contains = |spans: &[SrcSpan]| {
+            first_overlap_index(spans, byte_index)
+                .is_some_and(|index| spans[index].start <= byte_index)
         };
 
-        self.comments.binary_search_by(cmp).is_ok()
-            || self.doc_comments.binary_search_by(cmp).is_ok()
-            || self.module_comments.binary_search_by(cmp).is_ok()
+        contains(&self.comments) || contains(&self.doc_comments) || contains(&self.module_comments)
     }
 
     pub fn has_comment_between(&self, start: u32, end: u32) -> bool {
@@ -43,45 +34,330 @@ impl ModuleExtra {
     /// Returns the first comment overlapping the given source locations (inclusive)
     /// Note that the returned span covers the text of the comment, not the `//`
     pub fn first_comment_between(&self, start: u32, end: u32) -> Option<SrcSpan> {
-        // Helper function to find a comment that is between the given start
-        // and end. Not guaranteed to find the first comment.
-        let find_comment_between = |comments: &[SrcSpan], start, end| -> Option<usize> {
-            if comments.is_empty() {
-                return None;
+        let index = first_overlap_index(&self.comments, start)?;
+        let comment = self.comments[index];
+        (comment.start <= end).then_some(comment)
+    }
+
+    /// Returns true if there is a blank line anywhere between the two byte
+    /// positions (inclusive), i.e. the gap is more than just a line break.
+    fn has_empty_line_between(&self, start: u32, end: u32) -> bool {
+        self.empty_lines
+            .iter()
+            .any(|&line| line >= start && line <= end)
+    }
+
+    /// Returns true if there is no line break between the two byte
+    /// positions, i.e. they sit on the same source line.
+    fn on_same_line(&self, start: u32, end: u32) -> bool {
+        !self
+            .new_lines
+            .iter()
+            .any(|&line| line >= start && line <= end)
+    }
+}
+
+/// The comments immediately attached to a node: those directly above it
+/// with no blank line in between (`leading`), and the one trailing it on
+/// the same line, or dangling just before a closing delimiter (`trailing`).
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct AttachedComments {
+    pub leading: Vec<SrcSpan>,
+    pub trailing: Vec<SrcSpan>,
+}
+
+/// Associates a node's [`SrcSpan`] with the comments that belong to it,
+/// rather than leaving the formatter (or any other tooling layer) to
+/// re-derive that adjacency from `comments` and `new_lines` itself.
+pub trait CommentContainer {
+    fn comments_for(&self, span: SrcSpan) -> AttachedComments;
+}
+
+impl CommentContainer for ModuleExtra {
+    fn comments_for(&self, span: SrcSpan) -> AttachedComments {
+        // `comments` is sorted and non-overlapping, so `end` is monotonic
+        // too: a single partition-point search (in the spirit of
+        // `first_overlap_index`, though on a different predicate — the
+        // boundary of "ends at or before a point" rather than "overlaps a
+        // range") finds the last candidate before seeding the backward walk.
+        let mut leading = Vec::new();
+        let mut cursor = span.start;
+        let before_start = self
+            .comments
+            .partition_point(|comment| comment.end <= span.start);
+        for comment in self.comments[..before_start].iter().rev() {
+            if self.has_empty_line_between(comment.end, cursor)
+                || self.lines_between(comment.end, cursor) > 0
+            {
+                break;
             }
+            leading.push(*comment);
+            cursor = comment.start;
+        }
+        leading.reverse();
+
+        let same_line_after = {
+            let index = self
+                .comments
+                .partition_point(|comment| comment.start < span.end);
+            self.comments
+                .get(index)
+                .filter(|comment| self.on_same_line(span.end, comment.start))
+        };
 
-            comments
-                .binary_search_by(|comment| {
-                    if comment.end < start {
-                        Ordering::Less
-                    } else if comment.start > end {
-                        Ordering::Greater
-                    } else {
-                        Ordering::Equal
-                    }
+        // Only the comment immediately before the closing delimiter counts
+        // as dangling: it must be the last one ending at or before
+        // `span.end`, with nothing — not just no blank line, but no other
+        // line of code — between its end and `span.end`.
+        let dangling_before_close = {
+            let before_end = self
+                .comments
+                .partition_point(|comment| comment.end <= span.end);
+            before_end
+                .checked_sub(1)
+                .and_then(|index| self.comments.get(index))
+                .filter(|comment| {
+                    comment.start >= span.start && self.lines_between(comment.end, span.end) == 0
                 })
-                .ok()
         };
 
-        // To find the first comment in the given span, we first see if we can
-        // find any comment at all in the span by binary-searching over the list
-        // of comments in the module. If we do, we need to see if any other
-        // comment appears earlier, so we do the same search using the sub-list
-        // of comments before the one we found.
-        //
-        // We repeat this, narrowing our search list each time, until we can't
-        // find any comment earlier than our best.
-        let mut first_index_so_far = None;
-        let mut search_list = &self.comments[..];
-        while let Some(index) = find_comment_between(search_list, start, end) {
-            first_index_so_far = Some(index);
-            search_list = search_list.get(0..index).unwrap_or(&[]);
+        let trailing = same_line_after
+            .or(dangling_before_close)
+            .copied()
+            .into_iter()
+            .collect();
+
+        AttachedComments { leading, trailing }
+    }
+}
+
+impl ModuleExtra {
+    /// Returns the spans of `comments` (not `doc_comments` or
+    /// `module_comments`) whose content looks like commented-out Gleam
+    /// code rather than prose, so the build can warn about them.
+    pub fn commented_out_code<'a>(&'a self, source: &'a str) -> impl Iterator<Item = SrcSpan> + 'a {
+        self.comments.iter().copied().filter(move |span| {
+            source
+                .get(span.start as usize..span.end as usize)
+                .is_some_and(looks_like_code)
+        })
+    }
+
+    /// Groups adjacent `doc_comments` (consecutive lines, via `new_lines`,
+    /// with no gap between them) and beautifies each group into a single
+    /// dedented documentation string, ready for markdown rendering.
+    pub fn normalized_doc_comments<'a>(&'a self, source: &'a str) -> Vec<NormalizedDocComment> {
+        self.doc_comment_groups()
+            .into_iter()
+            .map(|group| normalize_doc_comment_group(&group, source))
+            .collect()
+    }
+
+    /// Splits `doc_comments` into runs of lines with no gap between them.
+    fn doc_comment_groups(&self) -> Vec<Vec<SrcSpan>> {
+        let mut groups: Vec<Vec<SrcSpan>> = Vec::new();
+        for &span in &self.doc_comments {
+            let starts_new_group = match groups.last().and_then(|group| group.last()) {
+                Some(previous) => self.lines_between(previous.end, span.start) > 0,
+                None => true,
+            };
+            if starts_new_group {
+                groups.push(Vec::new());
+            }
+            groups.last_mut().expect("just pushed").push(span);
         }
+        groups
+    }
 
-        first_index_so_far
-            .and_then(|index| self.comments.get(index))
-            .copied()
+    /// Counts the line breaks strictly between the two byte positions.
+    fn lines_between(&self, start: u32, end: u32) -> usize {
+        self.new_lines
+            .iter()
+            .filter(|&&line| line > start && line < end)
+            .count()
+    }
+}
+
+/// A normalized documentation string built from a run of consecutive
+/// `///` comments, with the markers, common indentation, and a single
+/// leading/trailing blank line all stripped away.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NormalizedDocComment {
+    pub span: SrcSpan,
+    pub content: EcoString,
+}
+
+fn normalize_doc_comment_group(group: &[SrcSpan], source: &str) -> NormalizedDocComment {
+    let span = SrcSpan {
+        start: group.first().expect("non-empty group").start,
+        end: group.last().expect("non-empty group").end,
+    };
+
+    let stripped: Vec<&str> = group
+        .iter()
+        .map(|line| {
+            source
+                .get(line.start as usize..line.end as usize)
+                .unwrap_or_default()
+                .strip_prefix("///")
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let indent = stripped
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut lines: Vec<&str> = stripped
+        .iter()
+        .map(|line| line.get(indent.min(line.len())..).unwrap_or_default())
+        .collect();
+
+    if lines.first().is_some_and(|line| line.trim().is_empty()) {
+        let _ = lines.remove(0);
+    }
+    if lines.last().is_some_and(|line| line.trim().is_empty()) {
+        let _ = lines.pop();
+    }
+
+    NormalizedDocComment {
+        span,
+        content: EcoString::from(lines.join("\n")),
+    }
+}
+
+/// Finds the first span in a sorted, non-overlapping slice that could
+/// overlap `start`, i.e. the first whose end has reached `start`. Callers
+/// must still check `span.start <= start` (for a point) or `span.start <=
+/// range.end` (for a range) to confirm the candidate actually overlaps.
+fn first_overlap_index(spans: &[SrcSpan], start: u32) -> Option<usize> {
+    let index = spans.partition_point(|span| span.end < start);
+    (index < spans.len()).then_some(index)
+}
+
+/// Interval algebra over [`SrcSpan`], so callers can test overlap and
+/// slice a span into the parts before, inside, and after another one.
+pub trait SpanRange: Sized {
+    fn intersects(&self, other: &Self) -> bool;
+    fn intersection(&self, other: &Self) -> Option<Self>;
+    fn split(&self, other: &Self) -> (Option<Self>, Option<Self>, Option<Self>);
+}
+
+impl SpanRange for SrcSpan {
+    fn intersects(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        self.intersects(other).then(|| SrcSpan {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        })
+    }
+
+    fn split(&self, other: &Self) -> (Option<Self>, Option<Self>, Option<Self>) {
+        let before = (self.start < other.start).then(|| SrcSpan {
+            start: self.start,
+            end: other.start.min(self.end),
+        });
+        let after = (self.end > other.end).then(|| SrcSpan {
+            start: other.end.max(self.start),
+            end: self.end,
+        });
+
+        (before, self.intersection(other), after)
+    }
+}
+
+impl ModuleExtra {
+    /// Returns every comment (from `comments`, `doc_comments` and
+    /// `module_comments`, merged in source order) overlapping the
+    /// inclusive range `start..=end`, found by locating the first overlap
+    /// with a single binary search and then walking forward from there.
+    pub fn comments_between(&self, start: u32, end: u32) -> impl Iterator<Item = SrcSpan> {
+        let merged = self.merged_comment_spans();
+        let first = first_overlap_index(&merged, start).unwrap_or(merged.len());
+        merged
+            .into_iter()
+            .skip(first)
+            .take_while(move |comment| comment.start <= end)
+    }
+
+    /// All comment spans (`comments`, `doc_comments`, `module_comments`),
+    /// merged in source order. `comments`, `doc_comments` and
+    /// `module_comments` are each already sorted by `start`, so this is a
+    /// linear merge rather than a sort over the concatenation.
+    fn merged_comment_spans(&self) -> Vec<SrcSpan> {
+        merge_sorted_spans(
+            &merge_sorted_spans(&self.comments, &self.doc_comments),
+            &self.module_comments,
+        )
+    }
+}
+
+/// Merges two span slices that are each already sorted by `start`.
+fn merge_sorted_spans(a: &[SrcSpan], b: &[SrcSpan]) -> Vec<SrcSpan> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.iter();
+    let mut b = b.iter();
+    let mut next_a = a.next();
+    let mut next_b = b.next();
+
+    loop {
+        match (next_a, next_b) {
+            (Some(&x), Some(&y)) if x.start <= y.start => {
+                merged.push(x);
+                next_a = a.next();
+            }
+            (Some(_), Some(&y)) => {
+                merged.push(y);
+                next_b = b.next();
+            }
+            (Some(&x), None) => {
+                merged.push(x);
+                next_a = a.next();
+            }
+            (None, Some(&y)) => {
+                merged.push(y);
+                next_b = b.next();
+            }
+            (None, None) => break,
+        }
     }
+
+    merged
+}
+
+/// A cheap heuristic for whether the (already `//`-stripped) content of a
+/// comment reads like a fragment of Gleam source rather than a prose note.
+fn looks_like_code(text: &str) -> bool {
+    let text = text.trim();
+    if text.is_empty() {
+        return false;
+    }
+
+    // A capitalised sentence, or one ending in a full stop, reads as prose
+    // even if it happens to contain a stray `,` or bracket; either on its
+    // own is enough to suppress a false positive.
+    let starts_like_prose = text.chars().next().is_some_and(char::is_uppercase);
+    let ends_like_prose = text.ends_with('.') && !text.ends_with("..");
+    if starts_like_prose || ends_like_prose {
+        return false;
+    }
+
+    const CODE_MARKERS: [&str; 5] = ["fn ", "let ", "case ", "-> ", "= "];
+    let has_marker = CODE_MARKERS.iter().any(|marker| text.contains(marker));
+    let ends_like_code = text.ends_with(')') || text.ends_with(',');
+    let balanced_brackets = {
+        let opens = text.matches(['(', '[', '{']).count();
+        let closes = text.matches([')', ']', '}']).count();
+        opens > 0 && opens == closes
+    };
+
+    has_marker || ends_like_code || balanced_brackets
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -112,7 +388,10 @@ impl<'a> From<(&SrcSpan, &'a str)> for Comment<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ast::SrcSpan, parse::extra::ModuleExtra};
+    use crate::{
+        ast::SrcSpan,
+        parse::extra::{CommentContainer, ModuleExtra, SpanRange},
+    };
 
     fn set_up_extra() -> ModuleExtra {
         let mut extra = ModuleExtra::new();
@@ -174,4 +453,202 @@ mod tests {
             Some(SrcSpan { start: 60, end: 70 })
         ));
     }
+
+    #[test]
+    fn comments_for_collects_leading_comments_with_no_blank_line() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![
+            SrcSpan { start: 0, end: 10 },
+            SrcSpan { start: 11, end: 20 },
+        ];
+        extra.new_lines = vec![10, 20];
+        let node = SrcSpan { start: 21, end: 30 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(
+            attached.leading,
+            vec![
+                SrcSpan { start: 0, end: 10 },
+                SrcSpan { start: 11, end: 20 }
+            ]
+        );
+        assert_eq!(attached.trailing, vec![]);
+    }
+
+    #[test]
+    fn comments_for_stops_leading_comments_at_a_blank_line() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![
+            SrcSpan { start: 0, end: 10 },
+            SrcSpan { start: 20, end: 30 },
+        ];
+        extra.new_lines = vec![10, 15, 20];
+        extra.empty_lines = vec![15];
+        let node = SrcSpan { start: 31, end: 40 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.leading, vec![SrcSpan { start: 20, end: 30 }]);
+    }
+
+    #[test]
+    fn comments_for_collects_a_trailing_comment_on_the_same_line() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 10, end: 20 }];
+        let node = SrcSpan { start: 0, end: 8 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.trailing, vec![SrcSpan { start: 10, end: 20 }]);
+    }
+
+    #[test]
+    fn comments_for_does_not_attach_a_trailing_comment_on_the_next_line() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 10, end: 20 }];
+        extra.new_lines = vec![9];
+        let node = SrcSpan { start: 0, end: 8 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.trailing, vec![]);
+    }
+
+    #[test]
+    fn comments_for_attaches_a_comment_dangling_before_a_closing_delimiter() {
+        // foo(
+        //   1,
+        //   // dangling
+        // )
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 20, end: 29 }];
+        let node = SrcSpan { start: 0, end: 30 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.trailing, vec![SrcSpan { start: 20, end: 29 }]);
+    }
+
+    #[test]
+    fn comments_for_does_not_treat_a_leading_comment_as_dangling() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 0, end: 10 }];
+        extra.new_lines = vec![10];
+        let node = SrcSpan { start: 11, end: 30 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.leading, vec![SrcSpan { start: 0, end: 10 }]);
+        assert_eq!(attached.trailing, vec![]);
+    }
+
+    #[test]
+    fn comments_for_does_not_attach_a_comment_followed_by_more_code_as_dangling() {
+        // foo(
+        //   1, // comment
+        //   2,
+        // )
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 13, end: 20 }];
+        extra.new_lines = vec![4, 20, 25];
+        let node = SrcSpan { start: 0, end: 27 };
+
+        let attached = extra.comments_for(node);
+
+        assert_eq!(attached.trailing, vec![]);
+    }
+
+    #[test]
+    fn commented_out_code_flags_code_like_comments() {
+        let source = "// let x = 1\n// This explains the function below.\n";
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![
+            SrcSpan { start: 3, end: 12 },
+            SrcSpan { start: 16, end: 49 },
+        ];
+
+        let flagged: Vec<SrcSpan> = extra.commented_out_code(source).collect();
+
+        assert_eq!(flagged, vec![SrcSpan { start: 3, end: 12 }]);
+    }
+
+    #[test]
+    fn commented_out_code_ignores_doc_and_module_comments() {
+        let source = "/// let x = 1\n//// let y = 2\n";
+        let mut extra = ModuleExtra::new();
+        extra.doc_comments = vec![SrcSpan { start: 4, end: 13 }];
+        extra.module_comments = vec![SrcSpan { start: 19, end: 28 }];
+
+        assert_eq!(extra.commented_out_code(source).count(), 0);
+    }
+
+    #[test]
+    fn normalized_doc_comments_dedents_and_joins_a_group() {
+        let source = "/// Hello\n///   world\n";
+        let mut extra = ModuleExtra::new();
+        extra.doc_comments = vec![SrcSpan { start: 0, end: 9 }, SrcSpan { start: 10, end: 21 }];
+        extra.new_lines = vec![9, 21];
+
+        let normalized = extra.normalized_doc_comments(source);
+
+        assert_eq!(normalized.len(), 1);
+        assert_eq!(normalized[0].span, SrcSpan { start: 0, end: 21 });
+        assert_eq!(normalized[0].content, "Hello\n  world");
+    }
+
+    #[test]
+    fn normalized_doc_comments_splits_on_a_gap_between_blocks() {
+        let source = "/// First\n\n/// Second\n";
+        let mut extra = ModuleExtra::new();
+        extra.doc_comments = vec![SrcSpan { start: 0, end: 9 }, SrcSpan { start: 11, end: 21 }];
+        extra.new_lines = vec![9, 10, 21];
+
+        let normalized = extra.normalized_doc_comments(source);
+
+        assert_eq!(normalized.len(), 2);
+        assert_eq!(normalized[0].content, "First");
+        assert_eq!(normalized[1].content, "Second");
+    }
+
+    #[test]
+    fn span_intersects_and_intersection() {
+        let a = SrcSpan { start: 0, end: 10 };
+        let b = SrcSpan { start: 5, end: 15 };
+
+        assert!(a.intersects(&b));
+        assert_eq!(a.intersection(&b), Some(SrcSpan { start: 5, end: 10 }));
+
+        let c = SrcSpan { start: 20, end: 30 };
+        assert!(!a.intersects(&c));
+        assert_eq!(a.intersection(&c), None);
+    }
+
+    #[test]
+    fn span_split_around_an_overlapping_range() {
+        let whole = SrcSpan { start: 0, end: 20 };
+        let (before, overlap, after) = whole.split(&SrcSpan { start: 8, end: 12 });
+
+        assert_eq!(before, Some(SrcSpan { start: 0, end: 8 }));
+        assert_eq!(overlap, Some(SrcSpan { start: 8, end: 12 }));
+        assert_eq!(after, Some(SrcSpan { start: 12, end: 20 }));
+    }
+
+    #[test]
+    fn comments_between_merges_all_three_comment_kinds_in_order() {
+        let mut extra = ModuleExtra::new();
+        extra.comments = vec![SrcSpan { start: 30, end: 35 }];
+        extra.doc_comments = vec![SrcSpan { start: 0, end: 5 }];
+        extra.module_comments = vec![SrcSpan { start: 15, end: 20 }];
+
+        let found: Vec<SrcSpan> = extra.comments_between(10, 32).collect();
+
+        assert_eq!(
+            found,
+            vec![
+                SrcSpan { start: 15, end: 20 },
+                SrcSpan { start: 30, end: 35 }
+            ]
+        );
+    }
 }